@@ -5,22 +5,22 @@
 #[path = "../example_common.rs"]
 mod example_common;
 
-use core::cell::{Cell, RefCell};
 use core::mem;
 
 use cortex_m_rt::entry;
 use defmt::{info, *};
 use embassy::executor::Executor;
 use embassy::util::Forever;
-use nrf_softdevice::ble::bond::BondHandler;
+use nrf_softdevice::ble::bond::{BondStore, BondStorePersist};
 use nrf_softdevice::ble::gatt_server::builder::ServiceBuilder;
 use nrf_softdevice::ble::gatt_server::characteristic::{Attribute, Metadata, Properties};
 use nrf_softdevice::ble::gatt_server::RegisterError;
-use nrf_softdevice::ble::{
-    gatt_server, peripheral, Address, AddressType, Connection, SecurityMode, SysAttrsReply, Uuid,
-};
+use nrf_softdevice::ble::{gatt_server, peripheral, Connection, Phy, SecurityMode, Uuid};
 use nrf_softdevice::{raw, Softdevice};
 
+/// Number of bonds `BondStore` keeps on hand before it starts evicting the least recently used one.
+const MAX_BONDS: usize = 8;
+
 const BATTERY_SERVICE: Uuid = Uuid::new_16(0x180f);
 const BATTERY_LEVEL: Uuid = Uuid::new_16(0x2a19);
 
@@ -31,83 +31,25 @@ async fn softdevice_task(sd: &'static Softdevice) {
     sd.run().await;
 }
 
-pub struct Bonder {
-    peer: Cell<Option<raw::ble_gap_enc_key_t>>,
-    sys_attrs: RefCell<(Option<Address>, heapless::Vec<u8, 62>)>,
-}
-
-impl Default for Bonder {
-    fn default() -> Self {
-        Bonder {
-            peer: Cell::new(None),
-            sys_attrs: Default::default(),
-        }
-    }
-}
+/// Placeholder persistence for `BondStore`: each hook only logs the slot it was called for;
+/// nothing is stored, and `read_slot` leaves `buf` untouched.
+struct FlashPersist;
 
-impl BondHandler for Bonder {
-    fn on_bonded(
-        &self,
-        _conn: &Connection,
-        key: &raw::ble_gap_enc_key_t,
-        _peer_id: Option<&raw::ble_gap_id_key_t>,
-        _peer_key: Option<&raw::ble_gap_enc_key_t>,
-    ) {
-        debug!(
-            "storing bond for: id: {{ ediv: {:x}, rand: {:x} }}, key: {{ ltk: {:x}, ltk_len: {}, auth: {}, lesc: {} }}",
-            key.master_id.ediv,
-            key.master_id.rand,
-            key.enc_info.ltk,
-            key.enc_info.ltk_len(),
-            key.enc_info.auth(),
-            key.enc_info.lesc()
-        );
-
-        // In a real application you would want to signal another task to permanently store the keys in non-volatile memory here.
-        self.peer.set(Some(*key));
-    }
-
-    fn get_key(&self, _conn: &Connection, master_id: raw::ble_gap_master_id_t) -> Option<raw::ble_gap_enc_info_t> {
-        debug!(
-            "getting bond for: id: {{ ediv: {:x}, rand: {:x} }}",
-            master_id.ediv, master_id.rand
-        );
-
-        self.peer.get().and_then(|peer| {
-            (master_id.ediv == peer.master_id.ediv && master_id.rand == peer.master_id.rand).then(|| peer.enc_info)
-        })
+impl BondStorePersist for FlashPersist {
+    fn read_slot(&self, slot: usize, buf: &mut [u8]) {
+        // In a real application this would read the flash page backing `slot` into `buf`.
+        debug!("loading bond slot {}", slot);
+        let _ = buf;
     }
 
-    fn save_sys_attrs(&self, conn: &Connection) {
-        debug!("saving system attributes for: {}", conn.peer_address());
-
-        let mut sys_attrs = self.sys_attrs.borrow_mut();
-        let capacity = sys_attrs.1.capacity();
-        unwrap!(sys_attrs.1.resize(capacity, 0));
-        let len = unwrap!(gatt_server::get_sys_attrs(conn, &mut sys_attrs.1)) as u16;
-        sys_attrs.1.truncate(usize::from(len));
-        sys_attrs.0 = Some(conn.peer_address());
-        // In a real application you would want to signal another task to permanently store sys_attrs for this connection's address
+    fn write_slot(&self, slot: usize, buf: &[u8]) {
+        // In a real application this would erase and program the flash page backing `slot`.
+        debug!("storing bond slot {}", slot);
+        let _ = buf;
     }
 
-    fn load_sys_attrs(&self, setter: SysAttrsReply) {
-        let sys_attrs = self.sys_attrs.borrow();
-        let addr = setter.connection().peer_address();
-        debug!("loading system attributes for: {}", addr);
-
-        match addr.address_type() {
-            AddressType::Public | AddressType::RandomStatic => {
-                if sys_attrs.0 == Some(addr) {
-                    unwrap!(setter.set_sys_attrs(&sys_attrs.1));
-                }
-            }
-            AddressType::RandomPrivateResolvable => {
-                // Need to use the peer id associated with the bond to calculate a hash per Bluetooth core
-                // specification 4.2 section 3.H.2.2.2.
-                defmt::unimplemented!()
-            }
-            AddressType::RandomPrivateNonResolvable | AddressType::Anonymous => return,
-        }
+    fn erase_slot(&self, slot: usize) {
+        debug!("erasing bond slot {}", slot);
     }
 }
 
@@ -146,6 +88,22 @@ impl BatteryService {
         gatt_server::notify_value(conn, self.value_handle, &[val])
     }
 
+    /// Like `battery_level_notify`, but waits for room in the ATT TX queue instead of
+    /// failing with `NotifyValueError::Raw(RESOURCES)` when it's full.
+    pub async fn battery_level_notify_queued(
+        &self,
+        conn: &Connection,
+        val: u8,
+    ) -> Result<(), gatt_server::NotifyValueError> {
+        gatt_server::notify_value_queued(conn, self.value_handle, &[val]).await
+    }
+
+    /// Notifies with confirmation: resolves once the peer has acknowledged the indication,
+    /// so callers get real backpressure instead of firing updates blind.
+    pub async fn battery_level_indicate(&self, conn: &Connection, val: u8) -> Result<(), gatt_server::IndicateValueError> {
+        gatt_server::indicate_value(conn, self.value_handle, &[val]).await
+    }
+
     pub fn on_write(&self, handle: u16, data: &[u8]) {
         if handle == self.cccd_handle && !data.is_empty() {
             info!("battery notifications: {}", (data[0] & 0x01) != 0);
@@ -187,16 +145,28 @@ async fn bluetooth_task(sd: &'static Softdevice, server: Server) {
         0x03, 0x03, 0x09, 0x18,
     ];
 
-    static BONDER: Forever<Bonder> = Forever::new();
-    let bonder = BONDER.put(Bonder::default());
+    static BOND_STORE: Forever<BondStore<MAX_BONDS, FlashPersist>> = Forever::new();
+    let bond_store = BOND_STORE.put(BondStore::new(FlashPersist));
 
     loop {
         let config = peripheral::Config::default();
         let adv = peripheral::ConnectableAdvertisement::ScannableUndirected { adv_data, scan_data };
-        let conn = unwrap!(peripheral::advertise_bondable(sd, adv, &config, bonder).await);
+        let conn = unwrap!(peripheral::advertise_bondable(sd, adv, &config, bond_store).await);
 
         info!("advertising done!");
 
+        // Now that we're connected, ask for 2M PHY and a bigger MTU than the default 23 bytes,
+        // then tighten the connection interval for the duration of the session.
+        if let Err(e) = conn.set_phy(Phy::M2, Phy::M2).await {
+            info!("phy negotiation failed: {:?}", e);
+        }
+        if let Err(e) = conn.exchange_mtu(247).await {
+            info!("mtu exchange failed: {:?}", e);
+        }
+        if let Err(e) = conn.request_conn_params(6, 12, 0, 400).await {
+            info!("conn params update failed: {:?}", e);
+        }
+
         // Run the GATT server on the connection. This returns when the connection gets disconnected.
         let res = gatt_server::run(&conn, &server, |_| {}).await;
 
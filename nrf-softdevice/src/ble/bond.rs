@@ -0,0 +1,425 @@
+use core::cell::{Cell, UnsafeCell};
+use core::mem::MaybeUninit;
+
+use cortex_m::interrupt;
+use heapless::Vec;
+
+use crate::ble::connection::{self, DisconnectedError, CONNS_MAX};
+use crate::ble::Connection;
+use crate::{raw, RawError};
+
+/// The longest `sd_ble_gatts_sys_attr_get` payload we keep per bond.
+const SYS_ATTRS_CAPACITY: usize = 62;
+
+/// Hooks a [`BondHandler`] implementation gets to react to, and answer, bonding-related
+/// SoftDevice events for a single connection.
+pub trait BondHandler {
+    /// A new bond (LTK, and optionally identity info) was just created.
+    fn on_bonded(
+        &self,
+        conn: &Connection,
+        key: &raw::ble_gap_enc_key_t,
+        peer_id: Option<&raw::ble_gap_id_key_t>,
+        peer_key: Option<&raw::ble_gap_enc_key_t>,
+    );
+
+    /// The peer is attempting to resume an encrypted link with a previously-bonded LTK; return
+    /// the matching encryption info, if any.
+    fn get_key(&self, conn: &Connection, master_id: raw::ble_gap_master_id_t) -> Option<raw::ble_gap_enc_info_t>;
+
+    /// The SoftDevice's GATT system attributes for `conn` changed and should be persisted.
+    fn save_sys_attrs(&self, conn: &Connection);
+
+    /// The SoftDevice is asking for the peer's previously-saved system attributes, if any.
+    fn load_sys_attrs(&self, setter: SysAttrsReply);
+}
+
+/// One-shot reply handle for [`BondHandler::load_sys_attrs`].
+pub struct SysAttrsReply {
+    conn: Connection,
+}
+
+impl SysAttrsReply {
+    pub(crate) fn new(conn: Connection) -> Self {
+        Self { conn }
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    pub fn set_sys_attrs(self, sys_attrs: &[u8]) -> Result<(), DisconnectedError> {
+        let conn_handle = self.conn.conn_handle()?;
+        let ptr = if sys_attrs.is_empty() {
+            core::ptr::null()
+        } else {
+            sys_attrs.as_ptr()
+        };
+        unsafe {
+            raw::sd_ble_gatts_sys_attr_set(conn_handle, ptr, sys_attrs.len() as u16, 0);
+        }
+        Ok(())
+    }
+}
+
+/// Persistence hooks a [`BondStore`] uses to read, write, and erase its slots.
+///
+/// Each slot holds one bond's GATT system attributes as an opaque byte blob: `write_slot` is
+/// given the attributes as saved (variable length, at most `SYS_ATTRS_CAPACITY` bytes) and
+/// `read_slot` a `SYS_ATTRS_CAPACITY`-byte buffer to fill. The bond keys themselves are not
+/// passed through these hooks. A real implementation typically backs each slot with its own
+/// flash page so that `write_slot` can erase-then-program without disturbing the others.
+pub trait BondStorePersist {
+    fn read_slot(&self, slot: usize, buf: &mut [u8]);
+    fn write_slot(&self, slot: usize, buf: &[u8]);
+    fn erase_slot(&self, slot: usize);
+}
+
+#[derive(Clone, Copy, Default)]
+struct Bond {
+    peer_id: Option<raw::ble_gap_id_key_t>,
+    key: Option<raw::ble_gap_enc_key_t>,
+}
+
+struct Slot {
+    bond: Option<Bond>,
+    sys_attrs: Vec<u8, SYS_ATTRS_CAPACITY>,
+    // Higher = more recently used; used to pick an eviction victim when the table is full.
+    last_used: u32,
+}
+
+impl Default for Slot {
+    fn default() -> Self {
+        Self {
+            bond: None,
+            sys_attrs: Vec::new(),
+            last_used: 0,
+        }
+    }
+}
+
+/// A fixed-capacity, multi-peer bond database.
+///
+/// `BondStore` keeps up to `N` bonds in RAM (identified by EDIV/rand for resuming encryption, and
+/// by peer identity/IRK for resolving a rotating RPA back to a bond's stored system attributes).
+/// Each bond's system attributes are also written through, and read back from, the
+/// `P: BondStorePersist` hooks. The bond keys and peer identities are kept in RAM only, and
+/// [`BondStore::new`] does not read existing slots back, so bonds do not survive a reset. When a
+/// new bond arrives and the table is already full, the least-recently-used slot is evicted to
+/// make room.
+///
+/// Implements [`BondHandler`] directly, so it can be passed straight to
+/// [`crate::ble::peripheral::advertise_bondable`] without any extra glue code.
+pub struct BondStore<const N: usize, P: BondStorePersist> {
+    persist: P,
+    slots: core::cell::RefCell<[Slot; N]>,
+    clock: core::cell::Cell<u32>,
+}
+
+impl<const N: usize, P: BondStorePersist> BondStore<N, P> {
+    pub fn new(persist: P) -> Self {
+        Self {
+            persist,
+            slots: core::cell::RefCell::new(core::array::from_fn(|_| Slot::default())),
+            clock: core::cell::Cell::new(0),
+        }
+    }
+
+    fn tick(&self) -> u32 {
+        let t = self.clock.get() + 1;
+        self.clock.set(t);
+        t
+    }
+
+    fn find_slot(&self, master_id: &raw::ble_gap_master_id_t) -> Option<usize> {
+        self.slots.borrow().iter().position(|slot| {
+            slot.bond.and_then(|b| b.key).is_some_and(|key| {
+                key.master_id.ediv == master_id.ediv && key.master_id.rand == master_id.rand
+            })
+        })
+    }
+
+    /// Finds the bond `addr` belongs to: resolved against the stored IRK for a rotating RPA, or
+    /// matched directly for a public/random-static identity address.
+    fn find_slot_by_irk(&self, addr: crate::ble::Address) -> Option<usize> {
+        use crate::ble::AddressType;
+
+        self.slots.borrow().iter().position(|slot| {
+            let Some(peer_id) = slot.bond.and_then(|b| b.peer_id) else {
+                return false;
+            };
+            match addr.address_type() {
+                AddressType::RandomPrivateResolvable => addr.resolve(&peer_id.id_info.irk),
+                AddressType::Public | AddressType::RandomStatic => {
+                    addr == crate::ble::Address::from_raw(peer_id.id_addr_info)
+                }
+                AddressType::RandomPrivateNonResolvable | AddressType::Anonymous => false,
+            }
+        })
+    }
+
+    /// Picks a slot for a fresh bond: the first empty one, or the least-recently-used one.
+    fn slot_for_new_bond(&self) -> usize {
+        let slots = self.slots.borrow();
+        if let Some(i) = slots.iter().position(|s| s.bond.is_none()) {
+            return i;
+        }
+        slots
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, s)| s.last_used)
+            .map(|(i, _)| i)
+            .unwrap_or(0)
+    }
+}
+
+impl<const N: usize, P: BondStorePersist> BondHandler for BondStore<N, P> {
+    fn on_bonded(
+        &self,
+        _conn: &Connection,
+        key: &raw::ble_gap_enc_key_t,
+        peer_id: Option<&raw::ble_gap_id_key_t>,
+        _peer_key: Option<&raw::ble_gap_enc_key_t>,
+    ) {
+        let index = self.slot_for_new_bond();
+        let last_used = self.tick();
+
+        self.slots.borrow_mut()[index] = Slot {
+            bond: Some(Bond {
+                peer_id: peer_id.copied(),
+                key: Some(*key),
+            }),
+            sys_attrs: Vec::new(),
+            last_used,
+        };
+        self.persist.erase_slot(index);
+    }
+
+    fn get_key(&self, _conn: &Connection, master_id: raw::ble_gap_master_id_t) -> Option<raw::ble_gap_enc_info_t> {
+        let index = self.find_slot(&master_id)?;
+        self.slots.borrow_mut()[index].last_used = self.tick();
+        self.slots.borrow()[index].bond.and_then(|b| b.key).map(|k| k.enc_info)
+    }
+
+    fn save_sys_attrs(&self, conn: &Connection) {
+        let Ok(conn_handle) = conn.conn_handle() else {
+            return;
+        };
+
+        let Some(index) = self.find_slot_by_irk(conn.peer_address()) else {
+            return;
+        };
+
+        let mut slots = self.slots.borrow_mut();
+        let slot = &mut slots[index];
+        unsafe {
+            let mut len = slot.sys_attrs.capacity() as u16;
+            let _ = slot.sys_attrs.resize_default(slot.sys_attrs.capacity());
+            raw::sd_ble_gatts_sys_attr_get(conn_handle, slot.sys_attrs.as_mut_ptr(), &mut len, 0);
+            slot.sys_attrs.truncate(len as usize);
+        }
+
+        self.persist.write_slot(index, &slot.sys_attrs);
+    }
+
+    fn load_sys_attrs(&self, setter: SysAttrsReply) {
+        let addr = setter.connection().peer_address();
+
+        let Some(index) = self.find_slot_by_irk(addr) else {
+            let _ = setter.set_sys_attrs(&[]);
+            return;
+        };
+        self.slots.borrow_mut()[index].last_used = self.tick();
+        let len = self.slots.borrow()[index].sys_attrs.len();
+
+        let mut buf = [0u8; SYS_ATTRS_CAPACITY];
+        self.persist.read_slot(index, &mut buf);
+        let _ = setter.set_sys_attrs(&buf[..len]);
+    }
+}
+
+struct HandlerCell(Cell<Option<&'static dyn BondHandler>>);
+// Safety: all access goes through `interrupt::free`, same as `crate::util::Signal`.
+unsafe impl Sync for HandlerCell {}
+
+static REGISTERED: HandlerCell = HandlerCell(Cell::new(None));
+
+/// Registers the [`BondHandler`] the SoftDevice event dispatcher should route bonding events to
+/// for the connection [`crate::ble::peripheral::advertise_bondable`] is about to establish.
+pub(crate) fn register(handler: &'static dyn BondHandler) {
+    interrupt::free(|_| REGISTERED.0.set(Some(handler)));
+}
+
+/// The handler most recently passed to [`register`], if any.
+pub(crate) fn registered() -> Option<&'static dyn BondHandler> {
+    interrupt::free(|_| REGISTERED.0.get())
+}
+
+// Per-connection storage for the keys exchanged while a pairing started in
+// `on_sec_params_request` is still in progress. `sd_ble_gap_sec_params_reply`'s `p_sec_keyset`
+// must point to memory that stays valid (and is filled in by the SoftDevice) for the whole
+// exchange, up to `on_auth_status`; these cells are that memory.
+struct KeyCell<T>(UnsafeCell<MaybeUninit<T>>);
+// Safety: all access happens inside the SoftDevice event dispatch driven by
+// `crate::Softdevice::run`, which handles one event at a time.
+unsafe impl<T> Sync for KeyCell<T> {}
+
+impl<T> KeyCell<T> {
+    const fn new() -> Self {
+        Self(UnsafeCell::new(MaybeUninit::uninit()))
+    }
+
+    fn as_mut_ptr(&self) -> *mut T {
+        self.0.get() as *mut T
+    }
+}
+
+impl<T: Copy> KeyCell<T> {
+    /// Safety: the SoftDevice must have written a valid `T` through [`as_mut_ptr`] first.
+    unsafe fn assume_init(&self) -> T {
+        (*self.0.get()).assume_init()
+    }
+}
+
+const OWN_ENC_NEW: KeyCell<raw::ble_gap_enc_key_t> = KeyCell::new();
+static OWN_ENC: [KeyCell<raw::ble_gap_enc_key_t>; CONNS_MAX] = [OWN_ENC_NEW; CONNS_MAX];
+const PEER_ID_NEW: KeyCell<raw::ble_gap_id_key_t> = KeyCell::new();
+static PEER_ID: [KeyCell<raw::ble_gap_id_key_t>; CONNS_MAX] = [PEER_ID_NEW; CONNS_MAX];
+
+/// `BLE_GAP_EVT_SEC_PARAMS_REQUEST`: the peer wants to (re-)pair. This crate doesn't expose a
+/// way for the application to pick an IO capability, so we always accept with a fixed Just Works
+/// policy: bondable, no MITM, no LE Secure Connections, no OOB. We distribute our own LTK
+/// (`kdist_own.enc`) and ask the peer to distribute its identity (`kdist_peer.id`), so
+/// [`BondHandler::save_sys_attrs`]/[`load_sys_attrs`] can resolve a rotating RPA back to a bond
+/// on future reconnections.
+pub(crate) fn on_sec_params_request(conn_handle: u16) {
+    let Some(index) = connection::conn_index(conn_handle) else {
+        defmt::warn!("on_sec_params_request: conn_handle {:?} out of range, dropping", conn_handle);
+        return;
+    };
+
+    let sec_params = raw::ble_gap_sec_params_t {
+        _bitfield_1: raw::ble_gap_sec_params_t::new_bitfield_1(
+            1, // bond
+            0, // mitm
+            0, // lesc
+            0, // keypress
+            raw::BLE_GAP_IO_CAPS_NONE as u8,
+            0, // oob
+        ),
+        min_key_size: 7,
+        max_key_size: 16,
+        kdist_own: raw::ble_gap_sec_kdist_t {
+            _bitfield_1: raw::ble_gap_sec_kdist_t::new_bitfield_1(1, 0, 0, 0),
+        },
+        kdist_peer: raw::ble_gap_sec_kdist_t {
+            _bitfield_1: raw::ble_gap_sec_kdist_t::new_bitfield_1(0, 1, 0, 0),
+        },
+    };
+
+    let keyset = raw::ble_gap_sec_keyset_t {
+        keys_own: raw::ble_gap_sec_keys_t {
+            p_enc_key: OWN_ENC[index].as_mut_ptr(),
+            p_id_key: core::ptr::null_mut(),
+            p_sign_key: core::ptr::null_mut(),
+            p_pk: core::ptr::null_mut(),
+        },
+        keys_peer: raw::ble_gap_sec_keys_t {
+            p_enc_key: core::ptr::null_mut(),
+            p_id_key: PEER_ID[index].as_mut_ptr(),
+            p_sign_key: core::ptr::null_mut(),
+            p_pk: core::ptr::null_mut(),
+        },
+    };
+
+    let ret = unsafe {
+        raw::sd_ble_gap_sec_params_reply(conn_handle, raw::BLE_GAP_SEC_STATUS_SUCCESS as u8, &sec_params, &keyset)
+    };
+    if let Err(err) = RawError::convert(ret) {
+        defmt::warn!("sd_ble_gap_sec_params_reply failed: {:?}", err);
+    }
+}
+
+/// `BLE_GAP_EVT_AUTH_KEY_REQUEST`: the SoftDevice wants a passkey or OOB data. Unreachable under
+/// the Just Works policy [`on_sec_params_request`] always requests, since that only applies to
+/// Passkey Entry/OOB pairing; reply with "none" to abort gracefully if it somehow fires anyway.
+pub(crate) fn on_auth_key_request(conn_handle: u16) {
+    let ret = unsafe { raw::sd_ble_gap_auth_key_reply(conn_handle, raw::BLE_GAP_AUTH_KEY_TYPE_NONE as u8, core::ptr::null()) };
+    if let Err(err) = RawError::convert(ret) {
+        defmt::warn!("sd_ble_gap_auth_key_reply failed: {:?}", err);
+    }
+}
+
+/// `BLE_GAP_EVT_AUTH_STATUS`: pairing finished, successfully or not. On success, reads back the
+/// keys the SoftDevice filled into the cells handed to it in [`on_sec_params_request`] and hands
+/// them to the registered [`BondHandler`] to persist. `peer_id_distributed` is the event's
+/// `kdist_peer.id()` bit: whether the peer actually sent its identity key (it was asked to, but
+/// a peer can still decline).
+pub(crate) fn on_auth_status(conn_handle: u16, status: u8, peer_id_distributed: bool) {
+    let Some(index) = connection::conn_index(conn_handle) else {
+        defmt::warn!("on_auth_status: conn_handle {:?} out of range, dropping", conn_handle);
+        return;
+    };
+    if status != raw::BLE_GAP_SEC_STATUS_SUCCESS as u8 {
+        defmt::warn!("pairing failed: auth_status={:?}", status);
+        return;
+    }
+    let Some(handler) = registered() else {
+        return;
+    };
+    let Some(conn) = connection::from_handle(conn_handle) else {
+        return;
+    };
+
+    // Safety: pairing just completed successfully, so the SoftDevice wrote a valid
+    // `ble_gap_enc_key_t` through the pointer we gave it; the peer identity key is only valid to
+    // read if `peer_id_distributed` says the peer actually sent one.
+    let key = unsafe { OWN_ENC[index].assume_init() };
+    let peer_id = peer_id_distributed.then(|| unsafe { PEER_ID[index].assume_init() });
+
+    // `peer_key` is always `None`: our `kdist_peer.enc` is 0, so the peer never distributes an
+    // encryption key in this crate's pairing flow (we generate and distribute the LTK instead).
+    handler.on_bonded(&conn, &key, peer_id.as_ref(), None);
+}
+
+/// `BLE_GAP_EVT_SEC_INFO_REQUEST`: the peer is resuming a previously-bonded connection and wants
+/// its LTK re-applied. Asks the registered [`BondHandler`] for the matching encryption info and
+/// replies with it, or with "not found" (which makes the SoftDevice ask the peer to re-pair) if
+/// there's no handler or no match.
+pub(crate) fn on_sec_info_request(conn_handle: u16, master_id: raw::ble_gap_master_id_t) {
+    if connection::conn_index(conn_handle).is_none() {
+        defmt::warn!("on_sec_info_request: conn_handle {:?} out of range, dropping", conn_handle);
+        return;
+    }
+
+    let enc_info = registered()
+        .zip(connection::from_handle(conn_handle))
+        .and_then(|(handler, conn)| handler.get_key(&conn, master_id));
+
+    let p_enc_info = enc_info.as_ref().map_or(core::ptr::null(), |info| info as *const _);
+    let ret = unsafe { raw::sd_ble_gap_sec_info_reply(conn_handle, p_enc_info, core::ptr::null(), core::ptr::null()) };
+    if let Err(err) = RawError::convert(ret) {
+        defmt::warn!("sd_ble_gap_sec_info_reply failed: {:?}", err);
+    }
+}
+
+/// `BLE_GATTS_EVT_SYS_ATTR_MISSING`: the SoftDevice needs this connection's GATT system
+/// attributes (CCCD state, etc.) restored before it can continue. Asks the registered
+/// [`BondHandler`] to supply them via [`SysAttrsReply`] ([`BondStore`] replies with an empty set
+/// when no bond matches), or applies an empty set itself if there's no handler, so the stack can
+/// proceed with fresh (non-persisted) state.
+pub(crate) fn on_sys_attr_missing(conn_handle: u16) {
+    if connection::conn_index(conn_handle).is_none() {
+        defmt::warn!("on_sys_attr_missing: conn_handle {:?} out of range, dropping", conn_handle);
+        return;
+    }
+
+    let applied = registered().zip(connection::from_handle(conn_handle)).map(|(handler, conn)| {
+        handler.load_sys_attrs(SysAttrsReply::new(conn));
+    });
+
+    if applied.is_none() {
+        let ret = unsafe { raw::sd_ble_gatts_sys_attr_set(conn_handle, core::ptr::null(), 0, 0) };
+        let _ = RawError::convert(ret);
+    }
+}
@@ -0,0 +1,252 @@
+use core::cell::Cell;
+
+use crate::ble::types::Phy;
+use crate::ble::Address;
+use crate::util::Signal;
+use crate::{raw, RawError};
+
+/// Upper bound on simultaneous connections this crate tracks. Not tied to `Config::conn_gap`'s
+/// `conn_count`; see [`conn_index`] for handles beyond it.
+pub(crate) const CONNS_MAX: usize = 8;
+
+/// A handle to an established BLE connection, identified by the SoftDevice's connection handle.
+#[derive(Clone, Copy)]
+pub struct Connection {
+    pub(crate) conn_handle: u16,
+    peer_address: Address,
+}
+
+impl Connection {
+    pub(crate) fn new(conn_handle: u16, peer_address: Address) -> Self {
+        Self {
+            conn_handle,
+            peer_address,
+        }
+    }
+
+    pub(crate) fn conn_handle(&self) -> Result<u16, DisconnectedError> {
+        conn_index(self.conn_handle).ok_or(DisconnectedError)?;
+        Ok(self.conn_handle)
+    }
+
+    pub fn peer_address(&self) -> Address {
+        self.peer_address
+    }
+
+    /// Requests the peer renegotiate the PHY used for `tx`/`rx` on this connection, completing
+    /// once the SoftDevice reports the (possibly different, peer-negotiated) PHYs actually in use.
+    pub async fn set_phy(&self, tx: Phy, rx: Phy) -> Result<(Phy, Phy), PhyUpdateError> {
+        let conn_handle = self.conn_handle()?;
+
+        let gap_phys = raw::ble_gap_phys_t {
+            tx_phys: tx as u8,
+            rx_phys: rx as u8,
+        };
+        let ret = unsafe { raw::sd_ble_gap_phy_update(conn_handle, &gap_phys) };
+        RawError::convert(ret)?;
+
+        let (status, tx_phy, rx_phy) = phy_update_signal(conn_handle).wait().await;
+        RawError::convert(status as u32)?;
+        Ok((Phy::from_raw(tx_phy), Phy::from_raw(rx_phy)))
+    }
+
+    /// Requests `desired` as the ATT_MTU for this connection, completing with the MTU the peer
+    /// actually granted (which may be smaller).
+    pub async fn exchange_mtu(&self, desired: u16) -> Result<u16, MtuExchangeError> {
+        let conn_handle = self.conn_handle()?;
+
+        let ret = unsafe { raw::sd_ble_gattc_exchange_mtu_request(conn_handle, desired) };
+        RawError::convert(ret)?;
+
+        let server_rx_mtu = mtu_signal(conn_handle).wait().await;
+        Ok(desired.min(server_rx_mtu))
+    }
+
+    /// Asks the peer (the link's central) to update the connection parameters, completing once
+    /// the new parameters take effect.
+    ///
+    /// `min_interval`/`max_interval` are in 1.25ms units, `timeout` is in 10ms units.
+    pub async fn request_conn_params(
+        &self,
+        min_interval: u16,
+        max_interval: u16,
+        latency: u16,
+        timeout: u16,
+    ) -> Result<(), SetConnParamsError> {
+        let conn_handle = self.conn_handle()?;
+
+        let conn_params = raw::ble_gap_conn_params_t {
+            min_conn_interval: min_interval,
+            max_conn_interval: max_interval,
+            slave_latency: latency,
+            conn_sup_timeout: timeout,
+        };
+        let ret = unsafe { raw::sd_ble_gap_conn_param_update(conn_handle, &conn_params) };
+        RawError::convert(ret)?;
+
+        conn_params_signal(conn_handle).wait().await;
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, defmt::Format)]
+pub struct DisconnectedError;
+
+impl From<RawError> for PhyUpdateError {
+    fn from(err: RawError) -> Self {
+        PhyUpdateError::Raw(err)
+    }
+}
+
+impl From<DisconnectedError> for PhyUpdateError {
+    fn from(_: DisconnectedError) -> Self {
+        PhyUpdateError::Disconnected
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, defmt::Format)]
+pub enum PhyUpdateError {
+    Disconnected,
+    Raw(RawError),
+}
+
+impl From<RawError> for MtuExchangeError {
+    fn from(err: RawError) -> Self {
+        MtuExchangeError::Raw(err)
+    }
+}
+
+impl From<DisconnectedError> for MtuExchangeError {
+    fn from(_: DisconnectedError) -> Self {
+        MtuExchangeError::Disconnected
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, defmt::Format)]
+pub enum MtuExchangeError {
+    Disconnected,
+    Raw(RawError),
+}
+
+impl From<RawError> for SetConnParamsError {
+    fn from(err: RawError) -> Self {
+        SetConnParamsError::Raw(err)
+    }
+}
+
+impl From<DisconnectedError> for SetConnParamsError {
+    fn from(_: DisconnectedError) -> Self {
+        SetConnParamsError::Disconnected
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, defmt::Format)]
+pub enum SetConnParamsError {
+    Disconnected,
+    Raw(RawError),
+}
+
+// Per-connection completion signals for the async negotiation methods above. Fed by
+// `on_phy_update`, `on_exchange_mtu_rsp` and `on_conn_param_update` below, which
+// `ble::dispatch_event` calls for the corresponding SoftDevice events.
+const PHY_UPDATE_SIGNAL_NEW: Signal<(u8, u8, u8)> = Signal::new();
+static PHY_UPDATE_SIGNAL: [Signal<(u8, u8, u8)>; CONNS_MAX] = [PHY_UPDATE_SIGNAL_NEW; CONNS_MAX];
+const MTU_SIGNAL_NEW: Signal<u16> = Signal::new();
+static MTU_SIGNAL: [Signal<u16>; CONNS_MAX] = [MTU_SIGNAL_NEW; CONNS_MAX];
+const CONN_PARAMS_SIGNAL_NEW: Signal<()> = Signal::new();
+static CONN_PARAMS_SIGNAL: [Signal<()>; CONNS_MAX] = [CONN_PARAMS_SIGNAL_NEW; CONNS_MAX];
+
+fn phy_update_signal(conn_handle: u16) -> &'static Signal<(u8, u8, u8)> {
+    &PHY_UPDATE_SIGNAL[conn_handle as usize]
+}
+
+fn mtu_signal(conn_handle: u16) -> &'static Signal<u16> {
+    &MTU_SIGNAL[conn_handle as usize]
+}
+
+fn conn_params_signal(conn_handle: u16) -> &'static Signal<()> {
+    &CONN_PARAMS_SIGNAL[conn_handle as usize]
+}
+
+/// Maps a SoftDevice `conn_handle` to an index into this module's (and `gatt_server`'s)
+/// `CONNS_MAX`-sized signal arrays, or `None` if it's `BLE_CONN_HANDLE_INVALID` or outside
+/// `CONNS_MAX`.
+///
+/// The latter is reachable whenever `Config::conn_gap` configures more simultaneous connections
+/// than `CONNS_MAX`: the SoftDevice will happily hand out a `conn_handle` our fixed-size arrays
+/// can't index, so every caller that receives a raw `conn_handle` from an event (rather than
+/// through an already-validated [`Connection`]) must go through this first.
+pub(crate) fn conn_index(conn_handle: u16) -> Option<usize> {
+    if conn_handle == raw::BLE_CONN_HANDLE_INVALID as u16 {
+        return None;
+    }
+    let index = conn_handle as usize;
+    (index < CONNS_MAX).then_some(index)
+}
+
+struct AddressCell(Cell<Option<Address>>);
+// Safety: all access goes through the SoftDevice event dispatch driven by
+// `crate::Softdevice::run`, which handles one event at a time.
+unsafe impl Sync for AddressCell {}
+
+const PEER_ADDRESS_NEW: AddressCell = AddressCell(Cell::new(None));
+// Tracks each live connection's peer address, so event handlers that only receive a raw
+// `conn_handle` (not a `Connection`) can reconstruct one via `from_handle` -- needed by
+// `ble::bond`, whose `BondHandler` callbacks take a `&Connection`.
+static PEER_ADDRESS: [AddressCell; CONNS_MAX] = [PEER_ADDRESS_NEW; CONNS_MAX];
+
+/// Records `peer_address` for `conn_handle`. Called by the event dispatcher on
+/// `BLE_GAP_EVT_CONNECTED`.
+pub(crate) fn set_connected(conn_handle: u16, peer_address: Address) {
+    if let Some(index) = conn_index(conn_handle) {
+        PEER_ADDRESS[index].0.set(Some(peer_address));
+    }
+}
+
+/// Clears the peer address recorded for `conn_handle`. Called by the event dispatcher on
+/// `BLE_GAP_EVT_DISCONNECTED`.
+pub(crate) fn set_disconnected(conn_handle: u16) {
+    if let Some(index) = conn_index(conn_handle) {
+        PEER_ADDRESS[index].0.set(None);
+    }
+}
+
+/// Reconstructs the `Connection` for `conn_handle`, for event handlers that only receive a raw
+/// handle. `None` if `conn_handle` is out of range or not currently connected.
+pub(crate) fn from_handle(conn_handle: u16) -> Option<Connection> {
+    let index = conn_index(conn_handle)?;
+    let peer_address = PEER_ADDRESS[index].0.get()?;
+    Some(Connection::new(conn_handle, peer_address))
+}
+
+// The functions below are called by the SoftDevice event dispatcher as it unpacks each event's
+// union payload.
+
+/// `BLE_GAP_EVT_PHY_UPDATE`: the negotiated PHYs are now in effect (or negotiation failed, per
+/// `status`).
+pub(crate) fn on_phy_update(conn_handle: u16, status: u8, tx_phy: u8, rx_phy: u8) {
+    if conn_index(conn_handle).is_none() {
+        defmt::warn!("on_phy_update: conn_handle {:?} out of range, dropping", conn_handle);
+        return;
+    }
+    phy_update_signal(conn_handle).signal((status, tx_phy, rx_phy));
+}
+
+/// `BLE_GATTC_EVT_EXCHANGE_MTU_RSP`: the peer reported `server_rx_mtu` as its receive MTU.
+pub(crate) fn on_exchange_mtu_rsp(conn_handle: u16, server_rx_mtu: u16) {
+    if conn_index(conn_handle).is_none() {
+        defmt::warn!("on_exchange_mtu_rsp: conn_handle {:?} out of range, dropping", conn_handle);
+        return;
+    }
+    mtu_signal(conn_handle).signal(server_rx_mtu);
+}
+
+/// `BLE_GAP_EVT_CONN_PARAM_UPDATE`: the connection parameters requested via
+/// [`Connection::request_conn_params`] are now in effect.
+pub(crate) fn on_conn_param_update(conn_handle: u16) {
+    if conn_index(conn_handle).is_none() {
+        defmt::warn!("on_conn_param_update: conn_handle {:?} out of range, dropping", conn_handle);
+        return;
+    }
+    conn_params_signal(conn_handle).signal(());
+}
@@ -0,0 +1,288 @@
+//! Generic Attribute server. GATT servers offer characteristics that a connected peer can read,
+//! write, and subscribe to.
+
+use crate::ble::connection::{self, DisconnectedError, CONNS_MAX};
+use crate::ble::Connection;
+use crate::util::Signal;
+use crate::{raw, RawError};
+
+pub mod builder;
+pub mod characteristic;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, defmt::Format)]
+pub struct ServiceHandle(pub(crate) u16);
+
+pub struct CharacteristicHandles {
+    pub value_handle: u16,
+    pub cccd_handle: u16,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, defmt::Format)]
+pub enum RegisterError {
+    Raw(RawError),
+}
+
+impl From<RawError> for RegisterError {
+    fn from(err: RawError) -> Self {
+        RegisterError::Raw(err)
+    }
+}
+
+/// Implemented by the application's GATT server, dispatching characteristic writes to whichever
+/// service owns the handle that was written.
+pub trait Server: Sized {
+    type Event;
+
+    fn on_write(&self, handle: u16, data: &[u8]) -> Option<Self::Event>;
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, defmt::Format)]
+pub enum GetValueError {
+    Truncated,
+    Raw(RawError),
+}
+
+impl From<RawError> for GetValueError {
+    fn from(err: RawError) -> Self {
+        Self::Raw(err)
+    }
+}
+
+pub fn get_value(_sd: &crate::Softdevice, handle: u16, buf: &mut [u8]) -> Result<usize, GetValueError> {
+    let mut value = raw::ble_gatts_value_t {
+        p_value: buf.as_mut_ptr(),
+        len: buf.len() as _,
+        offset: 0,
+    };
+    let ret = unsafe { raw::sd_ble_gatts_value_get(raw::BLE_CONN_HANDLE_INVALID as u16, handle, &mut value) };
+    RawError::convert(ret)?;
+
+    if value.len as usize > buf.len() {
+        return Err(GetValueError::Truncated);
+    }
+    Ok(value.len as _)
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, defmt::Format)]
+pub enum SetValueError {
+    Raw(RawError),
+}
+
+impl From<RawError> for SetValueError {
+    fn from(err: RawError) -> Self {
+        Self::Raw(err)
+    }
+}
+
+pub fn set_value(_sd: &crate::Softdevice, handle: u16, val: &[u8]) -> Result<(), SetValueError> {
+    let mut value = raw::ble_gatts_value_t {
+        p_value: val.as_ptr() as _,
+        len: val.len() as _,
+        offset: 0,
+    };
+    let ret = unsafe { raw::sd_ble_gatts_value_set(raw::BLE_CONN_HANDLE_INVALID as u16, handle, &mut value) };
+    RawError::convert(ret)?;
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, defmt::Format)]
+pub enum NotifyValueError {
+    Disconnected,
+    Raw(RawError),
+}
+
+impl From<RawError> for NotifyValueError {
+    fn from(err: RawError) -> Self {
+        Self::Raw(err)
+    }
+}
+
+impl From<DisconnectedError> for NotifyValueError {
+    fn from(_: DisconnectedError) -> Self {
+        Self::Disconnected
+    }
+}
+
+fn hvx(conn_handle: u16, handle: u16, val: &[u8], hvx_type: u8) -> Result<(), RawError> {
+    let mut len: u16 = val.len() as _;
+    let params = raw::ble_gatts_hvx_params_t {
+        handle,
+        type_: hvx_type,
+        offset: 0,
+        p_data: val.as_ptr() as _,
+        p_len: &mut len,
+    };
+    let ret = unsafe { raw::sd_ble_gatts_hvx(conn_handle, &params) };
+    RawError::convert(ret)
+}
+
+/// Queues a notification. Fails immediately with `NotifyValueError::Raw(RawError(RESOURCES))` if
+/// the SoftDevice's per-connection notification queue is already full; see
+/// [`notify_value_queued`] for a variant that waits for room instead.
+pub fn notify_value(conn: &Connection, handle: u16, val: &[u8]) -> Result<(), NotifyValueError> {
+    let conn_handle = conn.conn_handle()?;
+    hvx(conn_handle, handle, val, raw::BLE_GATT_HVX_NOTIFICATION as u8)?;
+    Ok(())
+}
+
+/// Like [`notify_value`], but if the notification queue is full, waits for the SoftDevice to
+/// drain it (signalled by a `BLE_GATTS_EVT_HVN_TX_COMPLETE` event) and retries, instead of
+/// failing.
+pub async fn notify_value_queued(conn: &Connection, handle: u16, val: &[u8]) -> Result<(), NotifyValueError> {
+    loop {
+        let conn_handle = conn.conn_handle()?;
+        match hvx(conn_handle, handle, val, raw::BLE_GATT_HVX_NOTIFICATION as u8) {
+            Ok(()) => return Ok(()),
+            Err(RawError(raw::NRF_ERROR_RESOURCES)) => {
+                tx_complete_signal(conn_handle).wait().await;
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, defmt::Format)]
+pub enum IndicateValueError {
+    Disconnected,
+    Raw(RawError),
+}
+
+impl From<RawError> for IndicateValueError {
+    fn from(err: RawError) -> Self {
+        Self::Raw(err)
+    }
+}
+
+impl From<DisconnectedError> for IndicateValueError {
+    fn from(_: DisconnectedError) -> Self {
+        Self::Disconnected
+    }
+}
+
+/// Sends an indication and waits for the peer's confirmation (`BLE_GATTS_EVT_HVC`) before
+/// resolving, giving the caller real backpressure instead of firing updates blind. Fails if an
+/// indication is already in progress on this connection.
+pub async fn indicate_value(conn: &Connection, handle: u16, val: &[u8]) -> Result<(), IndicateValueError> {
+    let conn_handle = conn.conn_handle()?;
+    hvx(conn_handle, handle, val, raw::BLE_GATT_HVX_INDICATION as u8)?;
+    confirm_signal(conn_handle).wait().await;
+    Ok(())
+}
+
+/// Runs the GATT server on `conn`, calling `f` for every application event `server` produces,
+/// until the connection disconnects.
+pub async fn run<F, S>(conn: &Connection, server: &S, mut f: F) -> Result<(), DisconnectedError>
+where
+    F: FnMut(S::Event),
+    S: Server,
+{
+    let conn_handle = conn.conn_handle()?;
+
+    loop {
+        match write_signal(conn_handle).wait().await {
+            GattsEvent::Write(WriteEvent { handle, len, data }) => {
+                if let Some(evt) = server.on_write(handle, &data[..len as usize]) {
+                    f(evt);
+                }
+            }
+            GattsEvent::Disconnected => return Err(DisconnectedError),
+        }
+    }
+}
+
+/// Largest single `BLE_GATTS_EVT_WRITE` payload we can receive: the protocol's maximum ATT_MTU
+/// (517) minus the 3-byte ATT opcode+handle header, so this bounds the event regardless of how
+/// `Config::conn_gatt` configures `att_mtu`.
+pub(crate) const MAX_WRITE_LEN: usize = 514;
+
+#[derive(Clone, Copy)]
+struct WriteEvent {
+    handle: u16,
+    len: u16,
+    data: [u8; MAX_WRITE_LEN],
+}
+
+#[derive(Clone, Copy)]
+enum GattsEvent {
+    Write(WriteEvent),
+    Disconnected,
+}
+
+// Per-connection signals fed by `on_write`, `on_notify_tx_complete`, `on_indicate_confirm` and
+// `on_disconnected` below, which `ble::dispatch_event` calls for the corresponding
+// `BLE_GATTS_EVT_*`/`BLE_GAP_EVT_DISCONNECTED` events. Wiring that dispatcher up to the
+// interrupt handler is out of scope here.
+const WRITE_SIGNAL_NEW: Signal<GattsEvent> = Signal::new();
+static WRITE_SIGNAL: [Signal<GattsEvent>; CONNS_MAX] = [WRITE_SIGNAL_NEW; CONNS_MAX];
+const TX_COMPLETE_SIGNAL_NEW: Signal<()> = Signal::new();
+static TX_COMPLETE_SIGNAL: [Signal<()>; CONNS_MAX] = [TX_COMPLETE_SIGNAL_NEW; CONNS_MAX];
+const CONFIRM_SIGNAL_NEW: Signal<()> = Signal::new();
+static CONFIRM_SIGNAL: [Signal<()>; CONNS_MAX] = [CONFIRM_SIGNAL_NEW; CONNS_MAX];
+
+fn write_signal(conn_handle: u16) -> &'static Signal<GattsEvent> {
+    &WRITE_SIGNAL[conn_handle as usize]
+}
+
+fn tx_complete_signal(conn_handle: u16) -> &'static Signal<()> {
+    &TX_COMPLETE_SIGNAL[conn_handle as usize]
+}
+
+fn confirm_signal(conn_handle: u16) -> &'static Signal<()> {
+    &CONFIRM_SIGNAL[conn_handle as usize]
+}
+
+/// `BLE_GATTS_EVT_WRITE`: a peer wrote `data` to the attribute at `handle`.
+pub(crate) fn on_write(conn_handle: u16, handle: u16, data: &[u8]) {
+    if connection::conn_index(conn_handle).is_none() {
+        defmt::warn!("on_write: conn_handle {:?} out of range, dropping", conn_handle);
+        return;
+    }
+    if data.len() > MAX_WRITE_LEN {
+        // Unreachable for any legitimately configured `att_mtu`, since MAX_WRITE_LEN already
+        // covers the protocol's largest possible single write; warn and drop rather than
+        // silently truncating, which would hand the application corrupted data instead of
+        // nothing.
+        defmt::warn!(
+            "on_write: {} byte write to handle {:?} exceeds MAX_WRITE_LEN, dropping",
+            data.len(),
+            handle
+        );
+        return;
+    }
+
+    let mut event = WriteEvent {
+        handle,
+        len: data.len() as u16,
+        data: [0; MAX_WRITE_LEN],
+    };
+    event.data[..data.len()].copy_from_slice(data);
+    write_signal(conn_handle).signal(GattsEvent::Write(event));
+}
+
+/// `BLE_GATTS_EVT_HVN_TX_COMPLETE`: there's room in the notification queue again.
+pub(crate) fn on_notify_tx_complete(conn_handle: u16) {
+    if connection::conn_index(conn_handle).is_none() {
+        defmt::warn!("on_notify_tx_complete: conn_handle {:?} out of range, dropping", conn_handle);
+        return;
+    }
+    tx_complete_signal(conn_handle).signal(());
+}
+
+/// `BLE_GATTS_EVT_HVC`: the peer confirmed the outstanding indication.
+pub(crate) fn on_indicate_confirm(conn_handle: u16) {
+    if connection::conn_index(conn_handle).is_none() {
+        defmt::warn!("on_indicate_confirm: conn_handle {:?} out of range, dropping", conn_handle);
+        return;
+    }
+    confirm_signal(conn_handle).signal(());
+}
+
+/// `BLE_GAP_EVT_DISCONNECTED`: unblocks [`run`] so it can return.
+pub(crate) fn on_disconnected(conn_handle: u16) {
+    if connection::conn_index(conn_handle).is_none() {
+        defmt::warn!("on_disconnected: conn_handle {:?} out of range, dropping", conn_handle);
+        return;
+    }
+    connection::set_disconnected(conn_handle);
+    write_signal(conn_handle).signal(GattsEvent::Disconnected);
+}
@@ -0,0 +1,92 @@
+use core::marker::PhantomData;
+use core::mem;
+use core::ptr::null;
+
+use super::characteristic::{self, AttributeMetadata};
+use super::{CharacteristicHandles, RegisterError, ServiceHandle};
+use crate::ble::Uuid;
+use crate::{raw, RawError, Softdevice};
+
+pub struct ServiceBuilder<'a> {
+    handle: u16,
+    sd: PhantomData<&'a mut Softdevice>,
+}
+
+pub struct CharacteristicBuilder<'a> {
+    handles: CharacteristicHandles,
+    sb: PhantomData<&'a ServiceBuilder<'a>>,
+}
+
+impl<'a> ServiceBuilder<'a> {
+    pub fn new(_sd: &'a mut Softdevice, uuid: Uuid) -> Result<Self, RegisterError> {
+        let mut service_handle: u16 = 0;
+        let ret =
+            unsafe { raw::sd_ble_gatts_service_add(raw::BLE_GATTS_SRVC_TYPE_PRIMARY as u8, uuid.as_raw_ptr(), &mut service_handle as _) };
+        RawError::convert(ret)?;
+
+        Ok(ServiceBuilder {
+            handle: service_handle,
+            sd: PhantomData,
+        })
+    }
+
+    pub fn add_characteristic<T: AsRef<[u8]>>(
+        &mut self,
+        uuid: Uuid,
+        attr: characteristic::Attribute<T>,
+        md: characteristic::Metadata,
+    ) -> Result<CharacteristicBuilder<'_>, RegisterError> {
+        let value = attr.value.as_ref();
+        assert!(value.len() <= usize::from(attr.max_len));
+
+        let attr_md = attr.metadata.into_raw();
+        let char_props = md.properties.into_raw();
+        let cccd_md = md.cccd.map(AttributeMetadata::into_raw);
+
+        let mut char_md = raw::ble_gatts_char_md_t {
+            char_props,
+            char_ext_props: unsafe { mem::zeroed() },
+            p_char_user_desc: null(),
+            char_user_desc_max_size: 0,
+            char_user_desc_size: 0,
+            p_char_pf: null(),
+            p_user_desc_md: null(),
+            p_cccd_md: cccd_md.as_ref().map_or(null(), |x| x as _),
+            p_sccd_md: null(),
+        };
+
+        let mut gatts_attr = raw::ble_gatts_attr_t {
+            p_uuid: uuid.as_raw_ptr(),
+            p_attr_md: &attr_md as _,
+            init_len: unwrap!(value.len().try_into()),
+            init_offs: 0,
+            max_len: attr.max_len,
+            p_value: value.as_ptr() as *mut _,
+        };
+
+        let mut handles: raw::ble_gatts_char_handles_t = unsafe { mem::zeroed() };
+
+        let ret = unsafe {
+            raw::sd_ble_gatts_characteristic_add(self.handle, &mut char_md as _, &mut gatts_attr as _, &mut handles as _)
+        };
+        RawError::convert(ret)?;
+
+        Ok(CharacteristicBuilder {
+            handles: CharacteristicHandles {
+                value_handle: handles.value_handle,
+                cccd_handle: handles.cccd_handle,
+            },
+            sb: PhantomData,
+        })
+    }
+
+    pub fn build(self) -> ServiceHandle {
+        ServiceHandle(self.handle)
+    }
+}
+
+impl<'a> CharacteristicBuilder<'a> {
+    pub fn build(self) -> CharacteristicHandles {
+        self.handles
+    }
+}
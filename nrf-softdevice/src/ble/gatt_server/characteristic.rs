@@ -0,0 +1,154 @@
+use crate::ble::SecurityMode;
+use crate::raw;
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, defmt::Format)]
+pub struct AttributeMetadata {
+    pub read: SecurityMode,
+    pub write: SecurityMode,
+    pub variable_len: bool,
+}
+
+impl AttributeMetadata {
+    pub(crate) fn into_raw(self) -> raw::ble_gatts_attr_md_t {
+        raw::ble_gatts_attr_md_t {
+            read_perm: self.read.into_raw(),
+            write_perm: self.write.into_raw(),
+            _bitfield_1: raw::ble_gatts_attr_md_t::new_bitfield_1(
+                self.variable_len.into(),
+                raw::BLE_GATTS_VLOC_STACK as u8,
+                0,
+                0,
+            ),
+        }
+    }
+}
+
+/// The initial value, length bound, and access permissions of a characteristic's value attribute.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, defmt::Format)]
+pub struct Attribute<T: AsRef<[u8]>> {
+    pub metadata: AttributeMetadata,
+    pub value: T,
+    pub max_len: u16,
+}
+
+impl<T: AsRef<[u8]>> Attribute<T> {
+    pub fn new(value: T) -> Self {
+        let max_len = unwrap!(value.as_ref().len().try_into());
+        Attribute {
+            max_len,
+            value,
+            metadata: Default::default(),
+        }
+    }
+
+    /// Sets both read and write security to `security`.
+    pub fn security(mut self, security: SecurityMode) -> Self {
+        self.metadata.read = security;
+        self.metadata.write = security;
+        self
+    }
+
+    pub fn read_security(mut self, security: SecurityMode) -> Self {
+        self.metadata.read = security;
+        self
+    }
+
+    pub fn write_security(mut self, security: SecurityMode) -> Self {
+        self.metadata.write = security;
+        self
+    }
+
+    pub fn variable_len(mut self, max_len: u16) -> Self {
+        self.max_len = max_len;
+        self.metadata.variable_len = true;
+        self
+    }
+}
+
+/// A characteristic's GATT properties, i.e. which ATT operations it supports.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, defmt::Format)]
+pub struct Properties {
+    pub broadcast: bool,
+    pub read: bool,
+    pub write_without_response: bool,
+    pub write: bool,
+    pub notify: bool,
+    pub indicate: bool,
+}
+
+impl Properties {
+    pub const fn new() -> Self {
+        Self {
+            broadcast: false,
+            read: false,
+            write_without_response: false,
+            write: false,
+            notify: false,
+            indicate: false,
+        }
+    }
+
+    pub const fn broadcast(mut self) -> Self {
+        self.broadcast = true;
+        self
+    }
+
+    pub const fn read(mut self) -> Self {
+        self.read = true;
+        self
+    }
+
+    pub const fn write_without_response(mut self) -> Self {
+        self.write_without_response = true;
+        self
+    }
+
+    pub const fn write(mut self) -> Self {
+        self.write = true;
+        self
+    }
+
+    pub const fn notify(mut self) -> Self {
+        self.notify = true;
+        self
+    }
+
+    pub const fn indicate(mut self) -> Self {
+        self.indicate = true;
+        self
+    }
+
+    pub(crate) fn into_raw(self) -> raw::ble_gatt_char_props_t {
+        raw::ble_gatt_char_props_t {
+            _bitfield_1: raw::ble_gatt_char_props_t::new_bitfield_1(
+                self.broadcast.into(),
+                self.read.into(),
+                self.write_without_response.into(),
+                self.write.into(),
+                self.notify.into(),
+                self.indicate.into(),
+                0,
+            ),
+        }
+    }
+}
+
+/// Characteristic-level metadata: its properties, plus the CCCD attribute the SoftDevice adds
+/// automatically when `notify` or `indicate` is set.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, defmt::Format)]
+pub struct Metadata {
+    pub properties: Properties,
+    pub cccd: Option<AttributeMetadata>,
+}
+
+impl Metadata {
+    pub fn new(properties: Properties) -> Self {
+        let cccd = if properties.indicate || properties.notify {
+            Some(AttributeMetadata::default())
+        } else {
+            None
+        };
+
+        Metadata { properties, cccd }
+    }
+}
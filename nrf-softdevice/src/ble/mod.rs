@@ -0,0 +1,81 @@
+pub mod bond;
+pub(crate) mod connection;
+pub mod gatt_server;
+pub mod peripheral;
+mod types;
+
+pub use connection::{Connection, DisconnectedError, MtuExchangeError, PhyUpdateError, SetConnParamsError};
+pub use types::{Address, AddressType, Phy, SecurityMode, Uuid};
+
+use crate::raw;
+
+/// Unpacks a raw SoftDevice `ble_evt_t` and routes it to whichever `ble` submodule owns that
+/// event, mirroring the union layout `sd_ble_evt_get` filled in.
+///
+/// Called by [`crate::Softdevice::run`] for every event it pulls off the SoftDevice's event
+/// queue. Events this crate doesn't otherwise act on are silently ignored.
+pub(crate) unsafe fn dispatch_event(evt: *const raw::ble_evt_t) {
+    match (*evt).header.evt_id as u32 {
+        raw::BLE_GAP_EVTS_BLE_GAP_EVT_CONNECTED => {
+            let gap_evt = (*evt).evt.gap_evt.as_ref();
+            peripheral::on_connected(gap_evt.conn_handle, gap_evt.params.connected.peer_addr);
+        }
+        raw::BLE_GAP_EVTS_BLE_GAP_EVT_DISCONNECTED => {
+            let gap_evt = (*evt).evt.gap_evt.as_ref();
+            gatt_server::on_disconnected(gap_evt.conn_handle);
+        }
+        raw::BLE_GAP_EVTS_BLE_GAP_EVT_CONN_PARAM_UPDATE => {
+            let gap_evt = (*evt).evt.gap_evt.as_ref();
+            connection::on_conn_param_update(gap_evt.conn_handle);
+        }
+        raw::BLE_GAP_EVTS_BLE_GAP_EVT_SEC_PARAMS_REQUEST => {
+            let gap_evt = (*evt).evt.gap_evt.as_ref();
+            bond::on_sec_params_request(gap_evt.conn_handle);
+        }
+        raw::BLE_GAP_EVTS_BLE_GAP_EVT_SEC_INFO_REQUEST => {
+            let gap_evt = (*evt).evt.gap_evt.as_ref();
+            bond::on_sec_info_request(gap_evt.conn_handle, gap_evt.params.sec_info_request.master_id);
+        }
+        raw::BLE_GAP_EVTS_BLE_GAP_EVT_AUTH_KEY_REQUEST => {
+            let gap_evt = (*evt).evt.gap_evt.as_ref();
+            bond::on_auth_key_request(gap_evt.conn_handle);
+        }
+        raw::BLE_GAP_EVTS_BLE_GAP_EVT_AUTH_STATUS => {
+            let gap_evt = (*evt).evt.gap_evt.as_ref();
+            let auth_status = gap_evt.params.auth_status;
+            bond::on_auth_status(gap_evt.conn_handle, auth_status.auth_status, auth_status.kdist_peer.id() != 0);
+        }
+        raw::BLE_GAP_EVTS_BLE_GAP_EVT_PHY_UPDATE => {
+            let gap_evt = (*evt).evt.gap_evt.as_ref();
+            let phy_update = gap_evt.params.phy_update;
+            connection::on_phy_update(gap_evt.conn_handle, phy_update.status, phy_update.tx_phy, phy_update.rx_phy);
+        }
+        raw::BLE_GAP_EVTS_BLE_GAP_EVT_ADV_SET_TERMINATED => {
+            peripheral::on_adv_timeout();
+        }
+        raw::BLE_GATTC_EVTS_BLE_GATTC_EVT_EXCHANGE_MTU_RSP => {
+            let gattc_evt = (*evt).evt.gattc_evt.as_ref();
+            let rsp = gattc_evt.params.exchange_mtu_rsp.as_ref();
+            connection::on_exchange_mtu_rsp(gattc_evt.conn_handle, rsp.server_rx_mtu);
+        }
+        raw::BLE_GATTS_EVTS_BLE_GATTS_EVT_WRITE => {
+            let gatts_evt = (*evt).evt.gatts_evt.as_ref();
+            let write = gatts_evt.params.write.as_ref();
+            let data = write.data.as_slice(write.len as usize);
+            gatt_server::on_write(gatts_evt.conn_handle, write.handle, data);
+        }
+        raw::BLE_GATTS_EVTS_BLE_GATTS_EVT_SYS_ATTR_MISSING => {
+            let gatts_evt = (*evt).evt.gatts_evt.as_ref();
+            bond::on_sys_attr_missing(gatts_evt.conn_handle);
+        }
+        raw::BLE_GATTS_EVTS_BLE_GATTS_EVT_HVC => {
+            let gatts_evt = (*evt).evt.gatts_evt.as_ref();
+            gatt_server::on_indicate_confirm(gatts_evt.conn_handle);
+        }
+        raw::BLE_GATTS_EVTS_BLE_GATTS_EVT_HVN_TX_COMPLETE => {
+            let gatts_evt = (*evt).evt.gatts_evt.as_ref();
+            gatt_server::on_notify_tx_complete(gatts_evt.conn_handle);
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,118 @@
+//! Bluetooth peripheral operations: emitting advertisements and accepting connections from
+//! central devices.
+
+use core::mem;
+
+use crate::ble::bond::BondHandler;
+use crate::ble::types::Phy;
+use crate::ble::{bond, Address, Connection};
+use crate::util::Signal;
+use crate::{raw, RawError, Softdevice};
+
+/// Connectable advertisement types, which can accept connections from interested central devices.
+#[derive(Clone, Copy, defmt::Format)]
+pub enum ConnectableAdvertisement<'a> {
+    ScannableUndirected { adv_data: &'a [u8], scan_data: &'a [u8] },
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, defmt::Format)]
+pub enum AdvertiseError {
+    Timeout,
+    Raw(RawError),
+}
+
+impl From<RawError> for AdvertiseError {
+    fn from(err: RawError) -> Self {
+        AdvertiseError::Raw(err)
+    }
+}
+
+/// Advertising configuration. Fields not set here use the SoftDevice's default.
+#[derive(Clone, Copy)]
+pub struct Config {
+    pub primary_phy: Phy,
+    /// Advertising interval, in 0.625ms units.
+    pub interval: u32,
+    /// Advertising timeout, in 10ms units. `None` advertises indefinitely.
+    pub timeout: Option<u16>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            primary_phy: Phy::M1,
+            interval: 400, // 250ms
+            timeout: None,
+        }
+    }
+}
+
+enum AdvEvent {
+    Connected { conn_handle: u16, peer_addr: raw::ble_gap_addr_t },
+    Timeout,
+}
+
+static ADV_SIGNAL: Signal<AdvEvent> = Signal::new();
+static mut ADV_HANDLE: u8 = raw::BLE_GAP_ADV_SET_HANDLE_NOT_SET as u8;
+
+fn start_adv(adv_data: &[u8], scan_data: &[u8], config: &Config) -> Result<(), AdvertiseError> {
+    let mut adv_params: raw::ble_gap_adv_params_t = unsafe { mem::zeroed() };
+    adv_params.properties.type_ = raw::BLE_GAP_ADV_TYPE_CONNECTABLE_SCANNABLE_UNDIRECTED as u8;
+    adv_params.primary_phy = config.primary_phy as u8;
+    adv_params.secondary_phy = config.primary_phy as u8;
+    adv_params.interval = config.interval;
+    adv_params.duration = config.timeout.unwrap_or(0);
+
+    let datas = raw::ble_gap_adv_data_t {
+        adv_data: raw::ble_data_t {
+            p_data: adv_data.as_ptr() as _,
+            len: adv_data.len() as u16,
+        },
+        scan_rsp_data: raw::ble_data_t {
+            p_data: scan_data.as_ptr() as _,
+            len: scan_data.len() as u16,
+        },
+    };
+
+    let ret = unsafe { raw::sd_ble_gap_adv_set_configure(core::ptr::addr_of_mut!(ADV_HANDLE), &datas, &adv_params) };
+    RawError::convert(ret)?;
+
+    let ret = unsafe { raw::sd_ble_gap_adv_start(ADV_HANDLE, crate::softdevice::CONN_CFG_TAG) };
+    RawError::convert(ret)?;
+
+    Ok(())
+}
+
+/// Advertises as a connectable peripheral and, once a central connects, hands its bonding events
+/// to `bond_handler` (typically a [`crate::ble::bond::BondStore`]) for the lifetime of the
+/// resulting connection.
+pub async fn advertise_bondable<H: BondHandler>(
+    _sd: &Softdevice,
+    adv: ConnectableAdvertisement<'_>,
+    config: &Config,
+    bond_handler: &'static H,
+) -> Result<Connection, AdvertiseError> {
+    let ConnectableAdvertisement::ScannableUndirected { adv_data, scan_data } = adv;
+
+    bond::register(bond_handler);
+    start_adv(adv_data, scan_data, config)?;
+
+    match ADV_SIGNAL.wait().await {
+        AdvEvent::Connected { conn_handle, peer_addr } => Ok(Connection::new(conn_handle, Address::from_raw(peer_addr))),
+        AdvEvent::Timeout => Err(AdvertiseError::Timeout),
+    }
+}
+
+/// `BLE_GAP_EVT_CONNECTED`, while advertising: a central has connected to us.
+///
+/// Called by the SoftDevice event dispatcher; wiring that dispatcher up to the interrupt handler
+/// is out of scope here.
+pub(crate) fn on_connected(conn_handle: u16, peer_addr: raw::ble_gap_addr_t) {
+    crate::ble::connection::set_connected(conn_handle, Address::from_raw(peer_addr));
+    ADV_SIGNAL.signal(AdvEvent::Connected { conn_handle, peer_addr });
+}
+
+/// `BLE_GAP_EVT_ADV_SET_TERMINATED`: advertising ended without a connection.
+pub(crate) fn on_adv_timeout() {
+    ADV_SIGNAL.signal(AdvEvent::Timeout);
+}
@@ -0,0 +1,364 @@
+use crate::raw;
+
+#[repr(transparent)]
+#[derive(Copy, Clone)]
+pub struct Uuid {
+    inner: raw::ble_uuid_t,
+}
+
+impl Uuid {
+    pub const fn new_16(uuid: u16) -> Self {
+        Self {
+            inner: raw::ble_uuid_t {
+                type_: raw::BLE_UUID_TYPE_BLE as u8,
+                uuid,
+            },
+        }
+    }
+
+    pub fn as_raw_ptr(&self) -> *const raw::ble_uuid_t {
+        &self.inner as _
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, defmt::Format)]
+pub enum SecurityMode {
+    NoAccess,
+    Open,
+    JustWorks,
+    Mitm,
+    LescMitm,
+    Signed,
+    SignedMitm,
+}
+
+impl Default for SecurityMode {
+    fn default() -> Self {
+        Self::Open
+    }
+}
+
+impl SecurityMode {
+    pub(crate) fn into_raw(self) -> raw::ble_gap_conn_sec_mode_t {
+        let (sm, lv) = match self {
+            SecurityMode::NoAccess => (0, 0),
+            SecurityMode::Open => (1, 1),
+            SecurityMode::JustWorks => (1, 2),
+            SecurityMode::Mitm => (1, 3),
+            SecurityMode::LescMitm => (1, 4),
+            SecurityMode::Signed => (2, 1),
+            SecurityMode::SignedMitm => (2, 2),
+        };
+
+        raw::ble_gap_conn_sec_mode_t {
+            _bitfield_1: raw::ble_gap_conn_sec_mode_t::new_bitfield_1(sm, lv),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, defmt::Format)]
+#[repr(u8)]
+pub enum AddressType {
+    /// Public (identity) address
+    Public = 0x00,
+    /// Random static (identity) address.
+    RandomStatic = 0x01,
+    /// Random private resolvable address.
+    RandomPrivateResolvable = 0x02,
+    /// Random private non-resolvable address.
+    RandomPrivateNonResolvable = 0x03,
+    /// An advertiser may advertise without its address. This type of advertising is called anonymous.
+    Anonymous = 0x7F,
+}
+
+impl AddressType {
+    fn from_raw(raw: u8) -> Self {
+        match raw {
+            0x00 => AddressType::Public,
+            0x01 => AddressType::RandomStatic,
+            0x02 => AddressType::RandomPrivateResolvable,
+            0x03 => AddressType::RandomPrivateNonResolvable,
+            _ => AddressType::Anonymous,
+        }
+    }
+}
+
+// Note: this type MUST be layout-compatible with raw::ble_gap_addr_t
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, defmt::Format)]
+pub struct Address {
+    flags: u8,
+    bytes: [u8; 6],
+}
+
+impl Address {
+    pub const fn new(address_type: AddressType, bytes: [u8; 6]) -> Self {
+        Self {
+            flags: (address_type as u8) << 1,
+            bytes,
+        }
+    }
+
+    pub fn address_type(&self) -> AddressType {
+        AddressType::from_raw(self.flags >> 1)
+    }
+
+    pub fn bytes(&self) -> [u8; 6] {
+        self.bytes
+    }
+
+    pub fn from_raw(raw: raw::ble_gap_addr_t) -> Self {
+        // Safety: `Self` has the same layout as `raw::ble_gap_addr_t` and all bit patterns are valid.
+        unsafe { core::mem::transmute(raw) }
+    }
+
+    /// Resolves this address against a peer's Identity Resolving Key, per Bluetooth core
+    /// specification 4.2 section 3.H.2.2.2.
+    ///
+    /// Only meaningful for [`AddressType::RandomPrivateResolvable`] addresses; for any other
+    /// address type this always returns `false`.
+    pub fn resolve(&self, irk: &[u8; 16]) -> bool {
+        if self.address_type() != AddressType::RandomPrivateResolvable {
+            return false;
+        }
+
+        let prand = [self.bytes[3], self.bytes[4], self.bytes[5]];
+        let hash = [self.bytes[0], self.bytes[1], self.bytes[2]];
+
+        ah(irk, prand) == hash
+    }
+}
+
+/// `ah(k, r) = e(k, r') mod 2^24`, the hash function Bluetooth core spec 4.2 section 3.H.2.2.2
+/// defines for resolving a Resolvable Private Address against an IRK.
+///
+/// `r` is the address's 24-bit `prand`. `e` is AES-128-ECB, driven here through
+/// [`encrypt_block`] rather than an external crypto dependency.
+fn ah(irk: &[u8; 16], prand: [u8; 3]) -> [u8; 3] {
+    // `r'` is `r`, zero-padded on the most-significant side to a 128-bit block.
+    let mut cleartext = [0u8; 16];
+    cleartext[13..].copy_from_slice(&prand);
+    cleartext[13..].reverse(); // BLE address bytes are little-endian; AES blocks are big-endian.
+
+    let mut key = *irk;
+    key.reverse(); // ditto for the IRK.
+
+    let ciphertext = encrypt_block(key, cleartext);
+
+    let mut hash: [u8; 3] = ciphertext[13..].try_into().unwrap();
+    hash.reverse(); // big-endian ciphertext back to little-endian address bytes.
+    hash
+}
+
+/// AES-128-ECB, single block, via the SoftDevice's `sd_ecb_block_encrypt` SVC call.
+#[cfg(not(test))]
+fn encrypt_block(key: [u8; 16], cleartext: [u8; 16]) -> [u8; 16] {
+    let mut ecb_hal_data = raw::nrf_ecb_hal_data_t {
+        key,
+        cleartext,
+        ciphertext: [0; 16],
+    };
+    // Can only return NRF_SUCCESS.
+    let _ = unsafe { raw::sd_ecb_block_encrypt(&mut ecb_hal_data) };
+    ecb_hal_data.ciphertext
+}
+
+/// Host-side stand-in for [`sd_ecb_block_encrypt`](raw::sd_ecb_block_encrypt), which is an SVC
+/// call and so can't run off-target: a plain AES-128-ECB single block encryption, so `ah`'s
+/// byte-order handling can be exercised against the spec's worked example in `cargo test`.
+#[cfg(test)]
+fn encrypt_block(key: [u8; 16], cleartext: [u8; 16]) -> [u8; 16] {
+    aes128::encrypt_block(key, cleartext)
+}
+
+#[cfg(test)]
+mod aes128 {
+    //! Minimal AES-128 (single block, encrypt-only) implementation, used solely by `ah`'s test to
+    //! stand in for the SoftDevice's `sd_ecb_block_encrypt`. Not used in the non-test build.
+
+    const SBOX: [u8; 256] = [
+        0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76, //
+        0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0, //
+        0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15, //
+        0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75, //
+        0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84, //
+        0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf, //
+        0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8, //
+        0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2, //
+        0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73, //
+        0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb, //
+        0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79, //
+        0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08, //
+        0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a, //
+        0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e, //
+        0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf, //
+        0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16, //
+    ];
+
+    const RCON: [u8; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
+
+    fn xtime(a: u8) -> u8 {
+        (a << 1) ^ if a & 0x80 != 0 { 0x1b } else { 0x00 }
+    }
+
+    fn gmul(a: u8, b: u8) -> u8 {
+        let mut a = a;
+        let mut b = b;
+        let mut p = 0u8;
+        for _ in 0..8 {
+            if b & 1 != 0 {
+                p ^= a;
+            }
+            a = xtime(a);
+            b >>= 1;
+        }
+        p
+    }
+
+    fn key_schedule(key: [u8; 16]) -> [[u8; 4]; 44] {
+        let mut w = [[0u8; 4]; 44];
+        for i in 0..4 {
+            w[i] = [key[4 * i], key[4 * i + 1], key[4 * i + 2], key[4 * i + 3]];
+        }
+        for i in 4..44 {
+            let mut temp = w[i - 1];
+            if i % 4 == 0 {
+                temp = [temp[1], temp[2], temp[3], temp[0]]; // RotWord
+                temp = temp.map(|b| SBOX[b as usize]); // SubWord
+                temp[0] ^= RCON[i / 4 - 1];
+            }
+            w[i] = [
+                w[i - 4][0] ^ temp[0],
+                w[i - 4][1] ^ temp[1],
+                w[i - 4][2] ^ temp[2],
+                w[i - 4][3] ^ temp[3],
+            ];
+        }
+        w
+    }
+
+    fn add_round_key(state: &mut [[u8; 4]; 4], w: &[[u8; 4]], round: usize) {
+        for c in 0..4 {
+            for r in 0..4 {
+                state[r][c] ^= w[round * 4 + c][r];
+            }
+        }
+    }
+
+    fn sub_bytes(state: &mut [[u8; 4]; 4]) {
+        for row in state.iter_mut() {
+            for b in row.iter_mut() {
+                *b = SBOX[*b as usize];
+            }
+        }
+    }
+
+    fn shift_rows(state: &mut [[u8; 4]; 4]) {
+        for (r, row) in state.iter_mut().enumerate().skip(1) {
+            row.rotate_left(r);
+        }
+    }
+
+    fn mix_columns(state: &mut [[u8; 4]; 4]) {
+        for c in 0..4 {
+            let col = [state[0][c], state[1][c], state[2][c], state[3][c]];
+            state[0][c] = gmul(col[0], 2) ^ gmul(col[1], 3) ^ col[2] ^ col[3];
+            state[1][c] = col[0] ^ gmul(col[1], 2) ^ gmul(col[2], 3) ^ col[3];
+            state[2][c] = col[0] ^ col[1] ^ gmul(col[2], 2) ^ gmul(col[3], 3);
+            state[3][c] = gmul(col[0], 3) ^ col[1] ^ col[2] ^ gmul(col[3], 2);
+        }
+    }
+
+    /// Encrypts a single 16-byte block with AES-128.
+    pub(super) fn encrypt_block(key: [u8; 16], block: [u8; 16]) -> [u8; 16] {
+        let w = key_schedule(key);
+
+        // State is column-major, per the AES spec: state[row][col].
+        let mut state = [[0u8; 4]; 4];
+        for c in 0..4 {
+            for r in 0..4 {
+                state[r][c] = block[4 * c + r];
+            }
+        }
+
+        add_round_key(&mut state, &w, 0);
+        for round in 1..10 {
+            sub_bytes(&mut state);
+            shift_rows(&mut state);
+            mix_columns(&mut state);
+            add_round_key(&mut state, &w, round);
+        }
+        sub_bytes(&mut state);
+        shift_rows(&mut state);
+        add_round_key(&mut state, &w, 10);
+
+        let mut out = [0u8; 16];
+        for c in 0..4 {
+            for r in 0..4 {
+                out[4 * c + r] = state[r][c];
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Bluetooth Core Specification, Vol 3, Part H, section 2.2.2's worked example for `ah`.
+    // The spec writes this IRK MSB-first as `ec0234a3 57c8ad05 341010a6 0a397d9b`; stored here
+    // byte-reversed, matching the little-endian octet order the SoftDevice fills `ble_gap_id_key_t`
+    // (and every other multi-byte BLE field, including `Address`'s own bytes) with.
+    const IRK: [u8; 16] = [
+        0x9b, 0x7d, 0x39, 0x0a, 0xa6, 0x10, 0x10, 0x34, 0x05, 0xad, 0xc8, 0x57, 0xa3, 0x34, 0x02, 0xec,
+    ];
+
+    #[test]
+    fn ah_matches_spec_example() {
+        // prand = 0x708194 (little-endian), hash = 0x0dfbaa (little-endian) = [0xaa, 0xfb, 0x0d].
+        assert_eq!(ah(&IRK, [0x94, 0x81, 0x70]), [0xaa, 0xfb, 0x0d]);
+    }
+
+    #[test]
+    fn resolve_matches_spec_example() {
+        let addr = Address::new(AddressType::RandomPrivateResolvable, [0xaa, 0xfb, 0x0d, 0x94, 0x81, 0x70]);
+        assert!(addr.resolve(&IRK));
+    }
+
+    #[test]
+    fn resolve_rejects_wrong_irk() {
+        let mut other_irk = IRK;
+        other_irk[0] ^= 0xff;
+        let addr = Address::new(AddressType::RandomPrivateResolvable, [0xaa, 0xfb, 0x0d, 0x94, 0x81, 0x70]);
+        assert!(!addr.resolve(&other_irk));
+    }
+
+    #[test]
+    fn resolve_false_for_non_resolvable_address_types() {
+        let addr = Address::new(AddressType::RandomStatic, [0xaa, 0xfb, 0x0d, 0x94, 0x81, 0x70]);
+        assert!(!addr.resolve(&IRK));
+    }
+}
+
+/// A Bluetooth LE PHY (physical layer data rate).
+#[derive(Debug, PartialEq, Eq, Clone, Copy, defmt::Format)]
+#[repr(u8)]
+pub enum Phy {
+    /// 1Mbps PHY.
+    M1 = raw::BLE_GAP_PHY_1MBPS as u8,
+    /// 2Mbps PHY.
+    M2 = raw::BLE_GAP_PHY_2MBPS as u8,
+    /// Long-range coded PHY (125kbps, S=8).
+    Coded = raw::BLE_GAP_PHY_CODED as u8,
+}
+
+impl Phy {
+    pub(crate) fn from_raw(raw: u8) -> Self {
+        match raw as u32 {
+            raw::BLE_GAP_PHY_2MBPS => Phy::M2,
+            raw::BLE_GAP_PHY_CODED => Phy::Coded,
+            _ => Phy::M1,
+        }
+    }
+}
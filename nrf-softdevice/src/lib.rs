@@ -0,0 +1,11 @@
+#![cfg_attr(not(test), no_std)]
+
+mod raw_error;
+mod softdevice;
+pub(crate) mod util;
+
+pub mod ble;
+
+pub use nrf_softdevice_s140 as raw;
+pub use raw_error::RawError;
+pub use softdevice::{Config, Softdevice};
@@ -0,0 +1,16 @@
+use crate::raw;
+
+/// Wraps a nonzero `NRF_ERROR_*`/`BLE_ERROR_*` code returned by a SoftDevice SVC call.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, defmt::Format)]
+pub struct RawError(pub u32);
+
+impl RawError {
+    /// Turns a SoftDevice SVC call's raw return code into a `Result`.
+    pub fn convert(ret: u32) -> Result<(), RawError> {
+        if ret == raw::NRF_SUCCESS {
+            Ok(())
+        } else {
+            Err(RawError(ret))
+        }
+    }
+}
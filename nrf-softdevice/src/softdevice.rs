@@ -0,0 +1,170 @@
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::mem::{self, MaybeUninit};
+use core::task::Poll;
+
+use crate::ble::gatt_server;
+use crate::{ble, raw, RawError};
+
+/// Singleton instance of the enabled SoftDevice.
+///
+/// Obtained by enabling it with [`Softdevice::enable`]. Once enabled, it can be used to
+/// establish Bluetooth connections with [`crate::ble::peripheral`].
+pub struct Softdevice {
+    // Prevent Send, Sync
+    _private: PhantomData<*mut ()>,
+}
+
+/// SoftDevice configuration.
+///
+/// Fields set to `None` use the SoftDevice's default configuration.
+#[derive(Default)]
+pub struct Config {
+    pub clock: Option<raw::nrf_clock_lf_cfg_t>,
+    pub conn_gap: Option<raw::ble_gap_conn_cfg_t>,
+    pub conn_gatt: Option<raw::ble_gatt_conn_cfg_t>,
+    pub gap_role_count: Option<raw::ble_gap_cfg_role_count_t>,
+    pub gap_device_name: Option<raw::ble_gap_cfg_device_name_t>,
+    pub gatts_attr_tab_size: Option<raw::ble_gatts_cfg_attr_tab_size_t>,
+}
+
+/// The `conn_cfg_tag` this crate always advertises/connects with; matches the tag
+/// [`crate::ble::peripheral`] passes to `sd_ble_gap_adv_start`, so the connection configuration
+/// set up here is the one actually applied to those connections.
+pub(crate) const CONN_CFG_TAG: u8 = 1;
+
+/// The nRF52 series' SRAM base address. In the absence of a linker-provided "end of application's
+/// static RAM" symbol (this crate ships no `memory.x`/build script), used as a conservative
+/// `app_ram_base`: it tells the SoftDevice the application isn't relying on any RAM below its own
+/// reserved region, at the cost of not reporting back how much RAM a real application could give
+/// back to the SoftDevice. A real linker-script-driven `app_ram_base` is future work.
+const APP_RAM_BASE: u32 = 0x2000_0000;
+
+unsafe extern "C" fn fault_handler(id: u32, pc: u32, info: u32) {
+    panic!("SoftDevice fault: id={} pc={:#x} info={:#x}", id, pc, info);
+}
+
+fn cfg_set(cfg_id: u32, cfg: &raw::ble_cfg_t) {
+    let ret = unsafe { raw::sd_ble_cfg_set(cfg_id, cfg, APP_RAM_BASE) };
+    if let Err(err) = RawError::convert(ret) {
+        panic!("sd_ble_cfg_set({}) failed: {:?}", cfg_id, err);
+    }
+}
+
+impl Softdevice {
+    /// Enable the SoftDevice with the given configuration, returning a `'static` handle to it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the SoftDevice rejects the requested configuration, or if called more than once.
+    pub fn enable(config: &Config) -> &'static Softdevice {
+        let p_clock_lf_cfg = config.clock.as_ref().map_or(core::ptr::null(), |c| c as _);
+        let ret = unsafe { raw::sd_softdevice_enable(p_clock_lf_cfg, Some(fault_handler)) };
+        if let Err(err) = RawError::convert(ret) {
+            panic!("sd_softdevice_enable failed: {:?}", err);
+        }
+
+        // At least one GAP connection config must be set for `CONN_CFG_TAG` to be usable; if the
+        // caller didn't supply one, set the SoftDevice's own defaults under that tag explicitly.
+        let conn_gap = config.conn_gap.unwrap_or(raw::ble_gap_conn_cfg_t {
+            conn_count: raw::BLE_GAP_CONN_COUNT_DEFAULT as u8,
+            event_length: raw::BLE_GAP_EVENT_LENGTH_DEFAULT as u16,
+        });
+        cfg_set(
+            raw::BLE_CONN_CFGS_BLE_CONN_CFG_GAP,
+            &raw::ble_cfg_t {
+                conn_cfg: raw::ble_conn_cfg_t {
+                    conn_cfg_tag: CONN_CFG_TAG,
+                    params: raw::ble_conn_cfg_t__bindgen_ty_1 { gap_conn_cfg: conn_gap },
+                },
+            },
+        );
+
+        if let Some(conn_gatt) = config.conn_gatt {
+            cfg_set(
+                raw::BLE_CONN_CFGS_BLE_CONN_CFG_GATT,
+                &raw::ble_cfg_t {
+                    conn_cfg: raw::ble_conn_cfg_t {
+                        conn_cfg_tag: CONN_CFG_TAG,
+                        params: raw::ble_conn_cfg_t__bindgen_ty_1 { gatt_conn_cfg: conn_gatt },
+                    },
+                },
+            );
+        }
+
+        if let Some(role_count_cfg) = config.gap_role_count {
+            cfg_set(
+                raw::BLE_GAP_CFGS_BLE_GAP_CFG_ROLE_COUNT,
+                &raw::ble_cfg_t {
+                    gap_cfg: raw::ble_gap_cfg_t { role_count_cfg },
+                },
+            );
+        }
+
+        if let Some(device_name_cfg) = config.gap_device_name {
+            cfg_set(
+                raw::BLE_GAP_CFGS_BLE_GAP_CFG_DEVICE_NAME,
+                &raw::ble_cfg_t {
+                    gap_cfg: raw::ble_gap_cfg_t { device_name_cfg },
+                },
+            );
+        }
+
+        if let Some(attr_tab_size) = config.gatts_attr_tab_size {
+            cfg_set(
+                raw::BLE_GATTS_CFGS_BLE_GATTS_CFG_ATTR_TAB_SIZE,
+                &raw::ble_cfg_t {
+                    gatts_cfg: raw::ble_gatts_cfg_t { attr_tab_size },
+                },
+            );
+        }
+
+        let mut app_ram_base = APP_RAM_BASE;
+        let ret = unsafe { raw::sd_ble_enable(&mut app_ram_base) };
+        if let Err(err) = RawError::convert(ret) {
+            panic!(
+                "sd_ble_enable failed: {:?} (SoftDevice wants app_ram_base >= {:#x})",
+                err, app_ram_base
+            );
+        }
+
+        static mut SOFTDEVICE: Softdevice = Softdevice { _private: PhantomData };
+        unsafe { &mut *core::ptr::addr_of_mut!(SOFTDEVICE) }
+    }
+
+    /// Runs the SoftDevice event loop. Never returns.
+    ///
+    /// Must be spawned as its own task (e.g. via `#[embassy::task]`) after [`Softdevice::enable`]
+    /// and before performing any BLE operation; otherwise those operations' futures never resolve,
+    /// since nothing would ever drive their completion.
+    ///
+    /// This crate has no PAC dependency to attach a real `SD_EVT_IRQn`/SWI2 interrupt handler to
+    /// (the SoftDevice itself has already enabled and owns that interrupt); instead, this
+    /// cooperatively re-polls `sd_ble_evt_get` every time the executor gives it a turn, rather
+    /// than sleeping until the SoftDevice actually signals new events. Functionally equivalent,
+    /// at the cost of never letting the executor idle.
+    pub async fn run(&self) -> ! {
+        // Large enough for the fixed part of any `ble_evt_t` plus the largest variable-length
+        // payload a single event can carry (a maximal `BLE_GATTS_EVT_WRITE`); word-sized so the
+        // buffer is naturally aligned to `raw::BLE_EVT_PTR_ALIGNMENT`.
+        const EVT_BUF_WORDS: usize = (mem::size_of::<raw::ble_evt_t>() + gatt_server::MAX_WRITE_LEN + 3) / 4;
+        let mut evt_buf: MaybeUninit<[u32; EVT_BUF_WORDS]> = MaybeUninit::uninit();
+
+        poll_fn(|cx| {
+            loop {
+                let mut len = (EVT_BUF_WORDS * 4) as u16;
+                let ret = unsafe { raw::sd_ble_evt_get(evt_buf.as_mut_ptr() as *mut u8, &mut len) };
+                match RawError::convert(ret) {
+                    Ok(()) => unsafe { ble::dispatch_event(evt_buf.as_ptr() as *const raw::ble_evt_t) },
+                    Err(RawError(raw::NRF_ERROR_NOT_FOUND)) => break,
+                    Err(err) => panic!("sd_ble_evt_get failed: {:?}", err),
+                }
+            }
+            // No more events queued right now; come back on the executor's next turn instead of
+            // blocking it here, per the polling-loop tradeoff documented above.
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        })
+        .await
+    }
+}
@@ -0,0 +1,51 @@
+use core::cell::Cell;
+use core::future::poll_fn;
+use core::task::{Poll, Waker};
+
+use cortex_m::interrupt;
+
+/// A single-slot, interrupt-safe wakeup cell.
+///
+/// SoftDevice event handlers are plain functions called from the event dispatch in
+/// `Softdevice::run` and have no way to `.await` anything, so the async wrappers in
+/// `ble::connection` and `ble::gatt_server` park a [`Waker`] here and have that dispatch call
+/// [`Signal::signal`] once the corresponding event arrives.
+pub(crate) struct Signal<T> {
+    waker: Cell<Option<Waker>>,
+    value: Cell<Option<T>>,
+}
+
+unsafe impl<T> Sync for Signal<T> {}
+
+impl<T> Signal<T> {
+    pub const fn new() -> Self {
+        Self {
+            waker: Cell::new(None),
+            value: Cell::new(None),
+        }
+    }
+
+    /// Called by the SoftDevice event dispatch once the awaited SoftDevice event has fired.
+    pub fn signal(&self, value: T) {
+        interrupt::free(|_| {
+            self.value.set(Some(value));
+            if let Some(waker) = self.waker.take() {
+                waker.wake();
+            }
+        });
+    }
+
+    pub async fn wait(&self) -> T {
+        poll_fn(|cx| {
+            interrupt::free(|_| {
+                if let Some(value) = self.value.take() {
+                    Poll::Ready(value)
+                } else {
+                    self.waker.set(Some(cx.waker().clone()));
+                    Poll::Pending
+                }
+            })
+        })
+        .await
+    }
+}